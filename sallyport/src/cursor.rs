@@ -0,0 +1,144 @@
+//! A bump-allocating cursor for packing pointer arguments into [`Block::buf`].
+//!
+//! [`Block`]'s doc comment says the microkernel "has copied the necessary
+//! data components into the `Block`'s `buf` field and has updated the `msg`
+//! register context fields accordingly" -- but doing that arithmetic by hand
+//! is error-prone for syscalls whose arguments are pointers (e.g. `write`,
+//! `read`, `openat`). [`Cursor`] does the bookkeeping instead: it borrows a
+//! `Block` and bump-allocates from the start of `buf`, handing back the
+//! *offset* of each allocation (not a raw pointer), since the host
+//! translates offsets relative to its own view of the shared buffer.
+
+use core::mem::{align_of, size_of};
+
+use crate::Block;
+
+/// Indicates a [`Cursor`] allocation would exceed `Block::buf_capacity()`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct OutOfSpace;
+
+/// A bump allocator over a [`Block`]'s `buf` field.
+///
+/// Every `alloc*` call checks capacity before advancing the cursor;
+/// nothing is ever freed individually; the whole `Block` is reused
+/// for the next request.
+pub struct Cursor<'a> {
+    block: &'a mut Block,
+    len: usize,
+}
+
+impl<'a> Cursor<'a> {
+    /// Creates a cursor over `block`, starting from the beginning of `buf`.
+    pub fn new(block: &'a mut Block) -> Self {
+        Self { block, len: 0 }
+    }
+
+    /// Bump-allocates `len` bytes from the buffer, returning their offset
+    /// within `buf` and a mutable view of the allocated region.
+    pub fn alloc(&mut self, len: usize) -> Result<(usize, &mut [u8]), OutOfSpace> {
+        let offset = self.len;
+        let end = offset.checked_add(len).ok_or(OutOfSpace)?;
+        if end > Block::buf_capacity() {
+            return Err(OutOfSpace);
+        }
+
+        self.len = end;
+        Ok((offset, &mut self.block.buf[offset..end]))
+    }
+
+    /// Bump-allocates `size_of::<T>()` bytes, first advancing the cursor to
+    /// satisfy `align_of::<T>()`.
+    pub fn alloc_aligned<T>(&mut self) -> Result<(usize, &mut [u8]), OutOfSpace> {
+        let align = align_of::<T>();
+        let aligned = self.len.checked_add(align - 1).ok_or(OutOfSpace)? & !(align - 1);
+        if aligned > Block::buf_capacity() {
+            return Err(OutOfSpace);
+        }
+
+        self.len = aligned;
+        self.alloc(size_of::<T>())
+    }
+
+    /// Copies `data` into the buffer and returns its offset.
+    pub fn write_slice(&mut self, data: &[u8]) -> Result<usize, OutOfSpace> {
+        let (offset, dst) = self.alloc(data.len())?;
+        dst.copy_from_slice(data);
+        Ok(offset)
+    }
+
+    /// Copies `value` into the buffer, aligned for `T`, and returns its offset.
+    pub fn write_struct<T: Copy>(&mut self, value: &T) -> Result<usize, OutOfSpace> {
+        let (offset, dst) = self.alloc_aligned::<T>()?;
+        // SAFETY: `dst` is exactly `size_of::<T>()` bytes, and `T: Copy`
+        // guarantees reading it as raw bytes is well-defined.
+        let src = unsafe {
+            core::slice::from_raw_parts(value as *const T as *const u8, size_of::<T>())
+        };
+        dst.copy_from_slice(src);
+        Ok(offset)
+    }
+
+    /// Finalizes the cursor, returning the total number of bytes allocated.
+    ///
+    /// Every `alloc*` call already checks capacity as it goes, so this
+    /// mainly guards against a future refactor breaking that invariant.
+    pub fn finish(self) -> Result<usize, OutOfSpace> {
+        if self.len > Block::buf_capacity() {
+            return Err(OutOfSpace);
+        }
+        Ok(self.len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem::MaybeUninit;
+
+    fn new_block() -> Block {
+        // SAFETY: `Block` is a plain data buffer; an all-zero instance is a
+        // valid starting point for these tests.
+        unsafe { MaybeUninit::zeroed().assume_init() }
+    }
+
+    #[test]
+    fn alloc_advances_and_reports_offset() {
+        let mut block = new_block();
+        let mut cursor = Cursor::new(&mut block);
+        let (offset, buf) = cursor.alloc(8).unwrap();
+        assert_eq!(offset, 0);
+        assert_eq!(buf.len(), 8);
+        let (offset, buf) = cursor.alloc(4).unwrap();
+        assert_eq!(offset, 8);
+        assert_eq!(buf.len(), 4);
+    }
+
+    #[test]
+    fn alloc_rejects_out_of_space() {
+        let mut block = new_block();
+        let mut cursor = Cursor::new(&mut block);
+        assert_eq!(
+            cursor.alloc(Block::buf_capacity() + 1),
+            Err(OutOfSpace)
+        );
+    }
+
+    #[test]
+    fn write_slice_stages_bytes() {
+        let mut block = new_block();
+        let mut cursor = Cursor::new(&mut block);
+        let offset = cursor.write_slice(b"hello").unwrap();
+        assert_eq!(&block.buf[offset..offset + 5], b"hello");
+    }
+
+    #[test]
+    fn write_struct_roundtrips() {
+        let mut block = new_block();
+        let mut cursor = Cursor::new(&mut block);
+        let offset = cursor.write_struct(&0x1122_3344u32).unwrap();
+        let len = cursor.finish().unwrap();
+        assert_eq!(len, offset + size_of::<u32>());
+        let bytes = &block.buf[offset..offset + size_of::<u32>()];
+        assert_eq!(u32::from_ne_bytes(bytes.try_into().unwrap()), 0x1122_3344);
+    }
+}
@@ -15,6 +15,14 @@
 use core::mem::size_of;
 use memory::{Page, Register};
 
+pub mod cursor;
+pub mod policy;
+pub mod queue;
+pub mod slab;
+
+#[cfg(feature = "testing")]
+pub mod testing;
+
 /// A request
 ///
 /// The `Request` struct is the most minimal representation of the register context
@@ -52,18 +60,83 @@ impl Request {
         }
     }
 
-    /// Issues the requested syscall and returns the reply
+    /// Creates a new request, refusing to do so if `policy` denies its syscall class.
+    ///
+    /// This is the checked counterpart to [`Request::new`]: it runs
+    /// [`policy::validate`] and consults `policy` before handing back a
+    /// `Request` at all. It's a chokepoint for callers that go through it,
+    /// not an enforcement mechanism: nothing stops other code from building
+    /// an unvalidated `Request` via [`Request::new`] or a struct literal and
+    /// passing it straight to [`Request::syscall`] instead. See the
+    /// [`policy`] module docs for what would actually be needed to enforce
+    /// a [`policy::Policy`] against a compromised guest.
+    #[inline]
+    pub fn validated(
+        policy: policy::Policy,
+        num: impl Into<Register<usize>>,
+        arg: &[Register<usize>],
+    ) -> Result<Self, policy::Denied> {
+        let req = Self::new(num, arg);
+        let class = policy::validate(&req)?;
+        if !policy.permits(class) {
+            return Err(policy::Denied::NotPermitted(class));
+        }
+        Ok(req)
+    }
+
+    /// Issues the requested syscall against the real host and returns the reply
+    ///
+    /// This is [`Request::syscall_via`] with [`HostProxy`] as the target;
+    /// downstream/microkernel code that wants to exercise its proxying logic
+    /// against a test double (see
+    /// [`testing::FakeHost`](crate::testing::FakeHost)) should call
+    /// `syscall_via` directly instead.
     ///
     /// # Safety
     ///
     /// This function is unsafe because syscalls can't be made generically safe.
     pub unsafe fn syscall(&self) -> Reply {
+        self.syscall_via(&HostProxy)
+    }
+
+    /// Issues the requested syscall through `proxy` and returns the reply.
+    ///
+    /// # Safety
+    ///
+    /// This function is unsafe because syscalls can't be made generically safe.
+    pub unsafe fn syscall_via(&self, proxy: &impl Proxy) -> Reply {
+        proxy.syscall(self)
+    }
+}
+
+/// Something that can service a [`Request`] and produce a [`Reply`].
+///
+/// [`Request::syscall`] normally reaches the real host through [`HostProxy`];
+/// factoring that dispatch out behind this trait lets downstream/microkernel
+/// code be exercised, via [`Request::syscall_via`], against a test double
+/// (see [`testing::FakeHost`](crate::testing::FakeHost)) instead of a real
+/// host.
+pub trait Proxy {
+    /// Services `req` and returns the resulting reply.
+    ///
+    /// # Safety
+    ///
+    /// This function is unsafe because syscalls can't be made generically safe.
+    unsafe fn syscall(&self, req: &Request) -> Reply;
+}
+
+/// The real host, reached via the `sallyport_syscall` FFI symbol.
+pub struct HostProxy;
+
+impl Proxy for HostProxy {
+    #[inline]
+    unsafe fn syscall(&self, req: &Request) -> Reply {
         extern "C" {
             fn sallyport_syscall(req: &Request, rep: &mut Reply);
         }
 
         let mut reply = core::mem::MaybeUninit::uninit().assume_init();
-        sallyport_syscall(self, &mut reply);
+        sallyport_syscall(req, &mut reply);
         reply
     }
 }
@@ -86,16 +159,52 @@ pub struct Reply {
     err: Register<usize>,
 }
 
-#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
-impl From<Result<[Register<usize>; 2], libc::c_int>> for Reply {
+/// Architecture-specific encoding of a proxied syscall's success/error
+/// convention into/out of a [`Reply`].
+///
+/// Most architectures collapse the result to a single register value, with
+/// error numbers above `-4096isize as usize`; `ppc64` instead uses the
+/// `cr0.SO` flag. This trait selects the correct threshold logic for the
+/// current target at compile time, behind the single pair of `From` impls
+/// below.
+trait ErrorEncoding {
+    /// Decodes a [`Reply`] into the syscall's logical result.
+    fn decode(reply: Reply) -> Result<[Register<usize>; 2], libc::c_int>;
+    /// Encodes the syscall's logical result into a [`Reply`].
+    fn encode(value: Result<[Register<usize>; 2], libc::c_int>) -> Reply;
+}
+
+/// The negative-errno convention used by x86_64 and aarch64: a return value
+/// above `-4096isize as usize` signals an error, whose errno is `-ret[0]`.
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+struct NegativeErrno;
+
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+impl ErrorEncoding for NegativeErrno {
     #[inline]
-    fn from(value: Result<[Register<usize>; 2], libc::c_int>) -> Self {
+    fn decode(reply: Reply) -> Result<[Register<usize>; 2], libc::c_int> {
+        let reg: usize = reply.ret[0].into();
+        if reg > -4096isize as usize {
+            Err(-(reg as libc::c_int))
+        } else {
+            Ok(reply.ret)
+        }
+    }
+
+    #[inline]
+    fn encode(value: Result<[Register<usize>; 2], libc::c_int>) -> Reply {
         match value {
-            Ok(val) => Self {
+            Ok(val) => Reply {
                 ret: val,
                 err: Default::default(),
             },
-            Err(val) => Self {
+            Err(val) => Reply {
                 ret: [(-val as usize).into(), Default::default()],
                 err: Default::default(),
             },
@@ -103,17 +212,75 @@ impl From<Result<[Register<usize>; 2], libc::c_int>> for Reply {
     }
 }
 
-#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
-impl From<Reply> for Result<[Register<usize>; 2], libc::c_int> {
+/// The `cr0.SO`-flag convention used by ppc64/ppc64le: `err != 0` signals an
+/// error, whose positive errno is carried in `ret[0]`.
+#[cfg(all(target_os = "linux", target_arch = "powerpc64"))]
+struct SummaryOverflow;
+
+#[cfg(all(target_os = "linux", target_arch = "powerpc64"))]
+impl ErrorEncoding for SummaryOverflow {
     #[inline]
-    fn from(value: Reply) -> Self {
-        let reg: usize = value.ret[0].into();
-        if reg > -4096isize as usize {
-            Err(-(reg as libc::c_int))
+    fn decode(reply: Reply) -> Result<[Register<usize>; 2], libc::c_int> {
+        let err: usize = reply.err.into();
+        if err != 0 {
+            let errno: usize = reply.ret[0].into();
+            Err(errno as libc::c_int)
         } else {
-            Ok(value.ret)
+            Ok(reply.ret)
         }
     }
+
+    #[inline]
+    fn encode(value: Result<[Register<usize>; 2], libc::c_int>) -> Reply {
+        match value {
+            Ok(val) => Reply {
+                ret: val,
+                err: Default::default(),
+            },
+            Err(val) => Reply {
+                ret: [(val as usize).into(), Default::default()],
+                err: 1usize.into(),
+            },
+        }
+    }
+}
+
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+impl From<Result<[Register<usize>; 2], libc::c_int>> for Reply {
+    #[inline]
+    fn from(value: Result<[Register<usize>; 2], libc::c_int>) -> Self {
+        NegativeErrno::encode(value)
+    }
+}
+
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+impl From<Reply> for Result<[Register<usize>; 2], libc::c_int> {
+    #[inline]
+    fn from(value: Reply) -> Self {
+        NegativeErrno::decode(value)
+    }
+}
+
+#[cfg(all(target_os = "linux", target_arch = "powerpc64"))]
+impl From<Result<[Register<usize>; 2], libc::c_int>> for Reply {
+    #[inline]
+    fn from(value: Result<[Register<usize>; 2], libc::c_int>) -> Self {
+        SummaryOverflow::encode(value)
+    }
+}
+
+#[cfg(all(target_os = "linux", target_arch = "powerpc64"))]
+impl From<Reply> for Result<[Register<usize>; 2], libc::c_int> {
+    #[inline]
+    fn from(value: Reply) -> Self {
+        SummaryOverflow::decode(value)
+    }
 }
 
 /// A message, which is either a request or a reply
@@ -199,4 +366,24 @@ mod tests {
         let res = Result::from(rep).unwrap()[0].into();
         assert_eq!(0usize, res);
     }
+
+    #[cfg(target_arch = "aarch64")]
+    #[test]
+    fn reply_roundtrip_negative_errno() {
+        let rep: Reply = Err::<[Register<usize>; 2], _>(libc::EBADF).into();
+        assert_eq!(Result::from(rep), Err(libc::EBADF));
+
+        let rep: Reply = Ok::<_, libc::c_int>([3usize.into(), 0usize.into()]).into();
+        assert_eq!(Result::from(rep), Ok([3usize.into(), 0usize.into()]));
+    }
+
+    #[cfg(target_arch = "powerpc64")]
+    #[test]
+    fn reply_roundtrip_summary_overflow() {
+        let rep: Reply = Err::<[Register<usize>; 2], _>(libc::EBADF).into();
+        assert_eq!(Result::from(rep), Err(libc::EBADF));
+
+        let rep: Reply = Ok::<_, libc::c_int>([3usize.into(), 0usize.into()]).into();
+        assert_eq!(Result::from(rep), Ok([3usize.into(), 0usize.into()]));
+    }
 }
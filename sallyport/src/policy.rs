@@ -0,0 +1,169 @@
+//! A typed, construction-time whitelist over the syscalls a [`Request`] may proxy.
+//!
+//! [`Request::syscall`][crate::Request::syscall] hands the raw `num` and
+//! seven argument registers straight to whichever [`Proxy`][crate::Proxy]
+//! services them, with no validation of its own. Mirroring the fixed,
+//! enumerated usercall surface the SGX ABI exposes, [`SyscallClass`]
+//! enumerates the calls Enarx actually proxies, and [`validate`] maps a
+//! `num` to its class while checking obvious argument sanity. Arity is not
+//! checked: the argument registers a class does not use are not inspected.
+//! [`Policy`] lets an embedder permit only a subset of classes, and
+//! [`crate::Request::validated`] refuses to build a `Request` for a denied
+//! class in the first place.
+//!
+//! This is a chokepoint for well-behaved callers building a `Request`
+//! through the normal constructors, not an enforcement mechanism a
+//! compromised guest can't route around: `Request`'s fields are `pub` (the
+//! syscall ABI is necessarily plain data), so nothing stops code that
+//! bypasses [`crate::Request::validated`] -- via a struct literal,
+//! [`crate::Request::new`], or a compromised guest -- from handing an
+//! unvalidated `Request` straight to a [`Proxy`][crate::Proxy]. Actually
+//! stopping that requires enforcement on whichever side services the
+//! `Request` (i.e. inside a `Proxy` implementation on the host), which is
+//! outside what this thin, `pub`-field ABI crate can do on its own.
+
+use crate::{Block, Request};
+
+/// The fixed set of syscalls Enarx proxies to the host.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum SyscallClass {
+    /// `read(2)`
+    Read,
+    /// `write(2)`
+    Write,
+    /// `close(2)`
+    Close,
+    /// `exit(2)` / `exit_group(2)`
+    Exit,
+    /// `clock_gettime(2)`
+    Clock,
+    /// `nanosleep(2)`
+    Nanosleep,
+}
+
+/// A bitset of [`SyscallClass`]es an embedder permits a guest to invoke.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub struct Policy(u32);
+
+impl Policy {
+    /// The empty policy, which denies every syscall class.
+    pub const NONE: Self = Self(0);
+
+    /// Returns a copy of this policy with `class` additionally permitted.
+    pub const fn allow(self, class: SyscallClass) -> Self {
+        Self(self.0 | (1 << class as u32))
+    }
+
+    /// Returns `true` if `class` is permitted by this policy.
+    pub const fn permits(&self, class: SyscallClass) -> bool {
+        self.0 & (1 << class as u32) != 0
+    }
+}
+
+/// Indicates a [`Request`] was refused by [`validate`] or a [`Policy`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Denied {
+    /// `num` does not correspond to any known [`SyscallClass`].
+    UnknownSyscall,
+    /// The syscall class is known, but not permitted by the active [`Policy`].
+    NotPermitted(SyscallClass),
+    /// An argument failed a sanity check (e.g. an out-of-range buffer offset).
+    InvalidArgument,
+}
+
+/// Maps `req.num` to its [`SyscallClass`] and checks its arguments for
+/// obvious sanity issues (buffer offsets within `Block::buf_capacity()`,
+/// non-negative file descriptors), independent of any [`Policy`].
+pub fn validate(req: &Request) -> Result<SyscallClass, Denied> {
+    let num: usize = req.num.into();
+    let class = match num {
+        n if n == libc::SYS_read as usize => SyscallClass::Read,
+        n if n == libc::SYS_write as usize => SyscallClass::Write,
+        n if n == libc::SYS_close as usize => SyscallClass::Close,
+        n if n == libc::SYS_exit as usize || n == libc::SYS_exit_group as usize => {
+            SyscallClass::Exit
+        }
+        n if n == libc::SYS_clock_gettime as usize => SyscallClass::Clock,
+        n if n == libc::SYS_nanosleep as usize => SyscallClass::Nanosleep,
+        _ => return Err(Denied::UnknownSyscall),
+    };
+
+    match class {
+        SyscallClass::Read | SyscallClass::Write => {
+            let fd: usize = req.arg[0].into();
+            if (fd as isize) < 0 {
+                return Err(Denied::InvalidArgument);
+            }
+
+            let offset: usize = req.arg[1].into();
+            let len: usize = req.arg[2].into();
+            let end = offset.checked_add(len).ok_or(Denied::InvalidArgument)?;
+            if end > Block::buf_capacity() {
+                return Err(Denied::InvalidArgument);
+            }
+        }
+        SyscallClass::Close => {
+            let fd: usize = req.arg[0].into();
+            if (fd as isize) < 0 {
+                return Err(Denied::InvalidArgument);
+            }
+        }
+        SyscallClass::Exit | SyscallClass::Clock | SyscallClass::Nanosleep => {}
+    }
+
+    Ok(class)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_known_syscalls() {
+        let req = Request::new(libc::SYS_close, &[0usize.into()]);
+        assert_eq!(validate(&req), Ok(SyscallClass::Close));
+    }
+
+    #[test]
+    fn rejects_unknown_syscall() {
+        let req = Request::new(libc::SYS_ptrace, &[]);
+        assert_eq!(validate(&req), Err(Denied::UnknownSyscall));
+    }
+
+    #[test]
+    fn rejects_negative_fd() {
+        let req = Request::new(libc::SYS_close, &[(-1isize as usize).into()]);
+        assert_eq!(validate(&req), Err(Denied::InvalidArgument));
+    }
+
+    #[test]
+    fn rejects_out_of_range_buffer_offset() {
+        let req = Request::new(
+            libc::SYS_write,
+            &[
+                1usize.into(),
+                Block::buf_capacity().into(),
+                1usize.into(),
+            ],
+        );
+        assert_eq!(validate(&req), Err(Denied::InvalidArgument));
+    }
+
+    #[test]
+    fn policy_permits_only_allowed_classes() {
+        let policy = Policy::NONE.allow(SyscallClass::Write);
+        assert!(policy.permits(SyscallClass::Write));
+        assert!(!policy.permits(SyscallClass::Read));
+    }
+
+    #[test]
+    fn validated_enforces_policy() {
+        let policy = Policy::NONE.allow(SyscallClass::Close);
+        assert!(Request::validated(policy, libc::SYS_close, &[0usize.into()]).is_ok());
+        assert_eq!(
+            Request::validated(policy, libc::SYS_write, &[0usize.into()]),
+            Err(Denied::NotPermitted(SyscallClass::Write))
+        );
+    }
+}
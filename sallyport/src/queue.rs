@@ -0,0 +1,197 @@
+//! Lock-free SPSC submission/completion queues layered over [`Request`] and
+//! [`Reply`].
+//!
+//! Today every proxied syscall goes through [`Request::syscall`][crate::Request::syscall],
+//! which blocks on a single `sallyport_syscall` round-trip to the host. For
+//! I/O-heavy workloads this serializes the enclave against the host on every
+//! call. This module lets the microkernel enqueue many [`Submission`]s into a
+//! [`Ring`] living in host-shared memory, and later drain the matching
+//! [`Completion`]s from a second `Ring`, amortizing the enclave/host
+//! transition across a batch.
+//!
+//! The existing synchronous path is unaffected by any of this and does not
+//! go through a `Ring`; with one request in flight at a time, it behaves
+//! like a single-slot (`N = 1`) `Ring`.
+
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{Reply, Request};
+
+/// A queued request, tagged with a caller-chosen token used to match it to
+/// its eventual [`Completion`] once the completion ring is drained.
+#[repr(C)]
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub struct Submission {
+    /// Caller-chosen correlation token, echoed back in the matching [`Completion`].
+    pub token: u64,
+
+    /// The request to be serviced by the host.
+    pub request: Request,
+}
+
+/// A completed request, tagged with the token supplied in the matching
+/// [`Submission`].
+#[repr(C)]
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub struct Completion {
+    /// The correlation token from the originating [`Submission`].
+    pub token: u64,
+
+    /// The reply produced by the host.
+    pub reply: Reply,
+}
+
+/// A lock-free single-producer/single-consumer ring buffer of `T`, with a
+/// capacity fixed at compile time by the const generic `N`, which must be a
+/// power of two.
+///
+/// `head` and `tail` are monotonically increasing counters rather than
+/// wrapped positions, masked against `N - 1` only when indexing into
+/// `slots`. Only the producer ever writes `tail`, and only the consumer
+/// ever writes `head`, so the two sides never contend for the same memory
+/// and no host-enclave lock is needed; the `Acquire`/`Release` pair on each
+/// index is what makes the corresponding slot write visible to the other
+/// side before it observes the updated index.
+pub struct Ring<T, const N: usize> {
+    head: AtomicUsize,
+    tail: AtomicUsize,
+    slots: [UnsafeCell<MaybeUninit<T>>; N],
+}
+
+// SAFETY: `Ring` is only ever accessed concurrently by (at most) one
+// producer and one consumer, each touching disjoint slots as governed by
+// `head`/`tail`, so sharing a `&Ring` across the two sides is sound as long
+// as `T` itself is `Send`.
+unsafe impl<T: Send, const N: usize> Sync for Ring<T, N> {}
+
+impl<T: Copy, const N: usize> Ring<T, N> {
+    const MASK: usize = N - 1;
+
+    /// Creates an empty ring.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is not a power of two.
+    pub fn new() -> Self {
+        assert!(N.is_power_of_two(), "Ring capacity must be a power of two");
+        Self {
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            // SAFETY: an array of `MaybeUninit` needs no initialization.
+            slots: unsafe { MaybeUninit::uninit().assume_init() },
+        }
+    }
+
+    /// Returns `true` if the ring holds no unread entries.
+    pub fn is_empty(&self) -> bool {
+        self.head.load(Ordering::Acquire) == self.tail.load(Ordering::Acquire)
+    }
+
+    /// Returns `true` if the ring has no free slots left to push into.
+    pub fn is_full(&self) -> bool {
+        self.tail.load(Ordering::Acquire) - self.head.load(Ordering::Acquire) == N
+    }
+
+    /// Pushes `value` onto the ring.
+    ///
+    /// Must only be called from the single producer. Returns `None`,
+    /// leaving `value` un-enqueued, if the ring is full.
+    pub fn push(&self, value: T) -> Option<()> {
+        let tail = self.tail.load(Ordering::Relaxed);
+        let head = self.head.load(Ordering::Acquire);
+        if tail - head == N {
+            return None;
+        }
+
+        let slot = &self.slots[tail & Self::MASK];
+        // SAFETY: only the producer writes to `slots[tail & MASK]`, and the
+        // consumer won't read it until it observes the `tail` store below.
+        unsafe { (*slot.get()).write(value) };
+        self.tail.store(tail + 1, Ordering::Release);
+        Some(())
+    }
+
+    /// Pops the oldest value off the ring.
+    ///
+    /// Must only be called from the single consumer. Returns `None` if the
+    /// ring is empty.
+    pub fn pop(&self) -> Option<T> {
+        let head = self.head.load(Ordering::Relaxed);
+        let tail = self.tail.load(Ordering::Acquire);
+        if head == tail {
+            return None;
+        }
+
+        let slot = &self.slots[head & Self::MASK];
+        // SAFETY: the `tail` load above observed this slot's producer write,
+        // and only the consumer reads or retires `slots[head & MASK]`.
+        let value = unsafe { (*slot.get()).assume_init_read() };
+        self.head.store(head + 1, Ordering::Release);
+        Some(value)
+    }
+}
+
+impl<T: Copy, const N: usize> Default for Ring<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The number of in-flight requests a [`SubmissionRing`]/[`CompletionRing`]
+/// pair can hold at once.
+pub const QUEUE_DEPTH: usize = 32;
+
+/// A ring of [`Submission`]s, written by the microkernel and drained by the host.
+pub type SubmissionRing = Ring<Submission, QUEUE_DEPTH>;
+
+/// A ring of [`Completion`]s, written by the host and drained by the microkernel.
+pub type CompletionRing = Ring<Completion, QUEUE_DEPTH>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_pop_roundtrip() {
+        let ring: Ring<u32, 4> = Ring::new();
+        assert!(ring.is_empty());
+        assert_eq!(ring.push(1), Some(()));
+        assert_eq!(ring.push(2), Some(()));
+        assert!(!ring.is_empty());
+        assert_eq!(ring.pop(), Some(1));
+        assert_eq!(ring.pop(), Some(2));
+        assert_eq!(ring.pop(), None);
+        assert!(ring.is_empty());
+    }
+
+    #[test]
+    fn full_ring_rejects_push() {
+        let ring: Ring<u32, 2> = Ring::new();
+        assert_eq!(ring.push(1), Some(()));
+        assert_eq!(ring.push(2), Some(()));
+        assert!(ring.is_full());
+        assert_eq!(ring.push(3), None);
+    }
+
+    #[test]
+    fn wraps_around_capacity() {
+        let ring: Ring<u32, 2> = Ring::new();
+        for i in 0..8 {
+            assert_eq!(ring.push(i), Some(()));
+            assert_eq!(ring.pop(), Some(i));
+        }
+    }
+
+    #[test]
+    fn submission_completion_roundtrip() {
+        let ring: SubmissionRing = Ring::new();
+        let submission = Submission {
+            token: 42,
+            request: Request::new(0usize, &[]),
+        };
+        assert_eq!(ring.push(submission), Some(()));
+        assert_eq!(ring.pop(), Some(submission));
+    }
+}
@@ -0,0 +1,313 @@
+//! A bitmap-backed slab allocator over [`Block::buf`], for scatter/gather
+//! syscalls.
+//!
+//! Syscalls like `readv`/`writev`/`sendmsg` take an array of `iovec`s, each
+//! pointing at its own region, and several of those regions may need to be
+//! staged simultaneously inside the single `buf`. A linear
+//! [`crate::cursor::Cursor`] can only grow, so it can't free and reuse a
+//! region once a batch of them has been consumed; this module divides `buf`
+//! into fixed-size chunks and tracks which are occupied in a bitmap, so
+//! individual regions can be freed independently via [`Slab::drop`].
+
+use core::mem::size_of;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::Block;
+
+/// The size, in bytes, of a single slab chunk. Allocations are rounded up to
+/// a whole number of chunks.
+pub const CHUNK: usize = 64;
+
+const TOTAL_CHUNKS: usize = Block::buf_capacity() / CHUNK;
+const WORDS: usize = TOTAL_CHUNKS.div_ceil(64);
+
+/// A bitmap-backed allocator that carves [`Block::buf`] into fixed-size
+/// chunks and hands out contiguous runs of them.
+///
+/// The total number of allocated chunks never exceeds
+/// `Block::buf_capacity() / CHUNK`, and [`SlabAllocator::alloc`] only ever
+/// returns a contiguous, chunk-aligned run, so the regions handed out never
+/// overlap.
+pub struct SlabAllocator {
+    bitmap: [AtomicU64; WORDS],
+}
+
+impl SlabAllocator {
+    /// Creates an allocator with every chunk free.
+    pub const fn new() -> Self {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const ZERO: AtomicU64 = AtomicU64::new(0);
+        Self {
+            bitmap: [ZERO; WORDS],
+        }
+    }
+
+    /// Allocates a contiguous run of chunks covering at least `len` bytes.
+    ///
+    /// Returns `None` if no contiguous run of free chunks large enough is
+    /// available.
+    pub fn alloc(&self, len: usize) -> Option<Slab<'_>> {
+        let chunks = len.div_ceil(CHUNK);
+
+        let mut run_start = 0;
+        let mut run_len = 0;
+        for chunk in 0..TOTAL_CHUNKS {
+            if self.is_free(chunk) {
+                if run_len == 0 {
+                    run_start = chunk;
+                }
+                run_len += 1;
+                if run_len == chunks.max(1) {
+                    self.mark(run_start, chunks, true);
+                    return Some(Slab {
+                        allocator: self,
+                        chunk: run_start,
+                        chunks,
+                        len,
+                    });
+                }
+            } else {
+                run_len = 0;
+            }
+        }
+
+        None
+    }
+
+    fn is_free(&self, chunk: usize) -> bool {
+        let (word, bit) = (chunk / 64, chunk % 64);
+        self.bitmap[word].load(Ordering::Acquire) & (1 << bit) == 0
+    }
+
+    fn mark(&self, start: usize, count: usize, occupied: bool) {
+        for chunk in start..start + count {
+            let (word, bit) = (chunk / 64, chunk % 64);
+            if occupied {
+                self.bitmap[word].fetch_or(1 << bit, Ordering::AcqRel);
+            } else {
+                self.bitmap[word].fetch_and(!(1 << bit), Ordering::AcqRel);
+            }
+        }
+    }
+}
+
+impl Default for SlabAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A contiguous, allocator-owned run of chunks within a [`Block::buf`].
+///
+/// The chunks are returned to the allocator's free bitmap when this value is
+/// dropped.
+pub struct Slab<'a> {
+    allocator: &'a SlabAllocator,
+    chunk: usize,
+    chunks: usize,
+    len: usize,
+}
+
+impl<'a> Slab<'a> {
+    /// The byte offset of this slab within `Block::buf`.
+    pub fn offset(&self) -> usize {
+        self.chunk * CHUNK
+    }
+
+    /// The number of bytes requested when this slab was allocated (may be
+    /// less than the number of chunks covered times `CHUNK`, since allocations round up to
+    /// whole chunks).
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if this slab covers zero bytes.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<'a> Drop for Slab<'a> {
+    fn drop(&mut self) {
+        if self.chunks > 0 {
+            self.allocator.mark(self.chunk, self.chunks, false);
+        }
+    }
+}
+
+/// The maximum number of regions an [`IovecBuilder`] can stage at once.
+const MAX_IOVECS: usize = 16;
+
+/// One entry of the offset/length table an [`IovecBuilder`] writes out,
+/// matching the shape of an `iovec` but holding a `Block`-relative offset
+/// rather than a pointer.
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct IovecEntry {
+    /// Offset of the region within `Block::buf`.
+    pub offset: u32,
+    /// Length of the region, in bytes.
+    pub len: u32,
+}
+
+/// Builds a scatter/gather `iovec` array out of a [`SlabAllocator`].
+///
+/// Each region staged via [`IovecBuilder::push`] is its own [`Slab`], so it
+/// can be sized independently of the others; [`IovecBuilder::finish`] writes
+/// the resulting offset/length table into one further slab and returns
+/// everything bundled as an [`Iovecs`], which keeps all of the underlying
+/// slabs alive (and hence un-freed) until the syscall they back has
+/// completed and `Iovecs` is dropped.
+pub struct IovecBuilder<'a> {
+    allocator: &'a SlabAllocator,
+    regions: [Option<Slab<'a>>; MAX_IOVECS],
+    count: usize,
+}
+
+impl<'a> IovecBuilder<'a> {
+    /// Creates an empty builder over `allocator`.
+    pub fn new(allocator: &'a SlabAllocator) -> Self {
+        Self {
+            allocator,
+            regions: core::array::from_fn(|_| None),
+            count: 0,
+        }
+    }
+
+    /// Allocates a region from the backing allocator, fills it with `data`,
+    /// and records it as the next iovec entry.
+    ///
+    /// Returns `None`, leaving the builder unchanged, if the allocator has
+    /// no room or `MAX_IOVECS` regions have already been staged.
+    pub fn push(&mut self, block: &mut Block, data: &[u8]) -> Option<()> {
+        if self.count >= MAX_IOVECS {
+            return None;
+        }
+
+        let slab = self.allocator.alloc(data.len())?;
+        let offset = slab.offset();
+        block.buf[offset..offset + data.len()].copy_from_slice(data);
+        self.regions[self.count] = Some(slab);
+        self.count += 1;
+        Some(())
+    }
+
+    /// Writes the offset/length table for every staged region into a final
+    /// slab, and bundles it with the staged regions as an [`Iovecs`].
+    ///
+    /// Takes `self` by value, so on success every staged [`Slab`] (plus the
+    /// new table slab) is moved into the returned [`Iovecs`] and kept alive.
+    /// Returns `None` if the allocator has no room left for the table
+    /// itself; in that case every region staged via [`IovecBuilder::push`]
+    /// is dropped along with `self` and released back to the allocator, so
+    /// the caller must build a new `IovecBuilder` and re-stage from
+    /// scratch rather than retrying `finish`.
+    pub fn finish(self, block: &mut Block) -> Option<Iovecs<'a>> {
+        let table_len = self.count * size_of::<IovecEntry>();
+        let table = self.allocator.alloc(table_len)?;
+
+        let mut cursor = table.offset();
+        for region in self.regions.iter().take(self.count) {
+            let region = region.as_ref().expect("staged slot is always Some");
+            let entry = IovecEntry {
+                offset: region.offset() as u32,
+                len: region.len() as u32,
+            };
+            // SAFETY: `IovecEntry` is `#[repr(C)]` and `Copy`, so reading it
+            // as raw bytes is well-defined.
+            let bytes = unsafe {
+                core::slice::from_raw_parts(&entry as *const _ as *const u8, size_of::<IovecEntry>())
+            };
+            block.buf[cursor..cursor + bytes.len()].copy_from_slice(bytes);
+            cursor += bytes.len();
+        }
+
+        Some(Iovecs {
+            table_offset: table.offset(),
+            table_len: self.count,
+            _regions: self.regions,
+            _table: table,
+        })
+    }
+}
+
+/// The slabs backing one scatter/gather syscall.
+///
+/// Keeps every staged region, and the offset/length table describing them,
+/// alive until this value is dropped -- typically once the matching `Reply`
+/// has been received and the regions are no longer needed.
+pub struct Iovecs<'a> {
+    /// Offset of the offset/length table within `Block::buf`; this is what a
+    /// `Request` argument should point at.
+    pub table_offset: usize,
+    /// Number of entries in the table.
+    pub table_len: usize,
+    _regions: [Option<Slab<'a>>; MAX_IOVECS],
+    _table: Slab<'a>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem::MaybeUninit;
+
+    fn new_block() -> Block {
+        // SAFETY: `Block` is a plain data buffer; an all-zero instance is a
+        // valid starting point for these tests.
+        unsafe { MaybeUninit::zeroed().assume_init() }
+    }
+
+    #[test]
+    fn alloc_rounds_up_to_chunk_and_frees_on_drop() {
+        let allocator = SlabAllocator::new();
+        {
+            let slab = allocator.alloc(1).unwrap();
+            assert_eq!(slab.offset(), 0);
+            assert_eq!(slab.len(), 1);
+            // The 1-byte slab took a whole chunk, so the next starts at CHUNK.
+            let next = allocator.alloc(1).unwrap();
+            assert_eq!(next.offset(), CHUNK);
+        }
+        // Dropped: the chunk should be reusable.
+        let slab = allocator.alloc(CHUNK).unwrap();
+        assert_eq!(slab.offset(), 0);
+    }
+
+    #[test]
+    fn allocations_never_overlap() {
+        let allocator = SlabAllocator::new();
+        let a = allocator.alloc(CHUNK).unwrap();
+        let b = allocator.alloc(CHUNK).unwrap();
+        assert_ne!(a.offset(), b.offset());
+    }
+
+    #[test]
+    fn exhausts_capacity() {
+        let allocator = SlabAllocator::new();
+        let mut slabs: [Option<Slab>; TOTAL_CHUNKS] = core::array::from_fn(|_| None);
+        let mut count = 0;
+        while let Some(slab) = allocator.alloc(CHUNK) {
+            slabs[count] = Some(slab);
+            count += 1;
+        }
+        assert_eq!(count, TOTAL_CHUNKS);
+        assert!(allocator.alloc(1).is_none());
+    }
+
+    #[test]
+    fn iovec_builder_stages_and_describes_regions() {
+        let allocator = SlabAllocator::new();
+        let mut block = new_block();
+        let mut builder = IovecBuilder::new(&allocator);
+        builder.push(&mut block, b"hello").unwrap();
+        builder.push(&mut block, b"world!").unwrap();
+        let iovecs = builder.finish(&mut block).unwrap();
+
+        assert_eq!(iovecs.table_len, 2);
+        let entry_size = size_of::<IovecEntry>();
+        let first = &block.buf[iovecs.table_offset..iovecs.table_offset + entry_size];
+        let offset = u32::from_ne_bytes(first[0..4].try_into().unwrap()) as usize;
+        let len = u32::from_ne_bytes(first[4..8].try_into().unwrap()) as usize;
+        assert_eq!(&block.buf[offset..offset + len], b"hello");
+    }
+}
@@ -0,0 +1,305 @@
+//! A fake host for deterministic testing of proxied syscalls.
+//!
+//! The only existing integration test, `syscall` in the crate root, requires
+//! a real `sallyport_syscall` symbol and performs actual `dup`/`close` calls
+//! against whatever host it runs on, so microkernel-side proxying logic
+//! can't be unit-tested deterministically. This follows the approach
+//! libtock-rs uses for its fake kernel: [`FakeHost`] implements the
+//! [`Proxy`][crate::Proxy] trait that [`Request::syscall_via`][crate::Request::syscall_via]
+//! dispatches through, in place of the real `sallyport_syscall` FFI call,
+//! answering a scripted, ordered list of [`ExpectedSyscall`]s instead. For
+//! syscalls that stage pointer arguments into `Block.buf` (e.g. via a
+//! [`crate::cursor::Cursor`]), [`FakeHost::expect_buf`] additionally scripts
+//! the bytes expected there, checked by [`FakeHost::syscall_with_buf`].
+
+use core::cell::RefCell;
+
+use crate::{Block, Proxy, Reply, Request};
+
+/// The bytes an [`ExpectedSyscall`] expects to find staged in `Block.buf`,
+/// e.g. by a [`crate::cursor::Cursor`], at the time of the call.
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct ExpectedBuf {
+    bytes: [u8; Block::buf_capacity()],
+    len: usize,
+}
+
+/// A single scripted syscall: the [`Request`] it expects to observe, the
+/// [`Reply`] to hand back once it does, and (if staged via
+/// [`FakeHost::expect_buf`]) the bytes expected in `Block.buf`.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct ExpectedSyscall {
+    /// The request this entry expects to observe.
+    pub request: Request,
+
+    /// The reply to return once the request matches.
+    pub reply: Reply,
+
+    buf: Option<ExpectedBuf>,
+}
+
+struct FakeHostState<const N: usize> {
+    expected: [ExpectedSyscall; N],
+    expected_len: usize,
+    next: usize,
+    log: [Request; N],
+    log_len: usize,
+}
+
+/// A fake host for deterministic testing of proxied syscalls.
+///
+/// Holds up to `N` scripted [`ExpectedSyscall`]s in order. Each call made
+/// through [`Proxy::syscall`] pops the next entry, asserts the incoming
+/// [`Request`] matches it, and returns the canned [`Reply`]; every observed
+/// request is also appended to [`FakeHost::log`]. Call [`FakeHost::finish`]
+/// once the code under test is done to assert nothing was left unconsumed.
+pub struct FakeHost<const N: usize> {
+    state: RefCell<FakeHostState<N>>,
+}
+
+impl<const N: usize> FakeHost<N> {
+    /// Creates a fake host with no scripted expectations.
+    pub fn new() -> Self {
+        Self {
+            state: RefCell::new(FakeHostState {
+                expected: [ExpectedSyscall::default(); N],
+                expected_len: 0,
+                next: 0,
+                log: [Request::default(); N],
+                log_len: 0,
+            }),
+        }
+    }
+
+    /// Appends an expectation to the end of the script.
+    ///
+    /// # Panics
+    ///
+    /// Panics if more than `N` expectations are queued.
+    pub fn expect(&self, request: Request, reply: Reply) {
+        self.push_expected(ExpectedSyscall {
+            request,
+            reply,
+            buf: None,
+        });
+    }
+
+    /// Appends an expectation to the end of the script, additionally
+    /// requiring `buf` to match `Block.buf` at the time of the call; see
+    /// [`FakeHost::syscall_with_buf`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if more than `N` expectations are queued, or if `buf` is
+    /// longer than `Block::buf_capacity()`.
+    pub fn expect_buf(&self, request: Request, buf: &[u8], reply: Reply) {
+        let mut bytes = [0u8; Block::buf_capacity()];
+        bytes[..buf.len()].copy_from_slice(buf);
+        self.push_expected(ExpectedSyscall {
+            request,
+            reply,
+            buf: Some(ExpectedBuf {
+                bytes,
+                len: buf.len(),
+            }),
+        });
+    }
+
+    fn push_expected(&self, entry: ExpectedSyscall) {
+        let mut state = self.state.borrow_mut();
+        let len = state.expected_len;
+        assert!(len < N, "FakeHost: no room for more than {} expectations", N);
+        state.expected[len] = entry;
+        state.expected_len = len + 1;
+    }
+
+    /// Asserts every scripted expectation was consumed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any expectation was left unconsumed.
+    pub fn finish(&self) {
+        let state = self.state.borrow();
+        assert_eq!(
+            state.next,
+            state.expected_len,
+            "FakeHost: {} expectation(s) left unconsumed",
+            state.expected_len - state.next
+        );
+    }
+
+    /// Returns the requests observed so far, padded with trailing
+    /// `Request::default()` entries; only the first [`FakeHost::log_len`]
+    /// of them are real. Prefer slicing: `&host.log()[..host.log_len()]`.
+    pub fn log(&self) -> [Request; N] {
+        self.state.borrow().log
+    }
+
+    /// The number of requests observed so far -- i.e. the valid prefix of
+    /// [`FakeHost::log`].
+    pub fn log_len(&self) -> usize {
+        self.state.borrow().log_len
+    }
+
+    /// Services `req`, as [`Proxy::syscall`] does, but also asserts any
+    /// buffer staged via [`FakeHost::expect_buf`] against `block`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the matched expectation staged bytes via
+    /// [`FakeHost::expect_buf`] and `block.buf` doesn't match them.
+    pub fn syscall_with_buf(&self, req: &Request, block: &Block) -> Reply {
+        self.dispatch(req, Some(block))
+    }
+
+    fn dispatch(&self, req: &Request, block: Option<&Block>) -> Reply {
+        let mut state = self.state.borrow_mut();
+        let next = state.next;
+        assert!(
+            next < state.expected_len,
+            "FakeHost: unexpected syscall {:?}, no expectations remain",
+            req
+        );
+
+        let expected = state.expected[next];
+        assert_eq!(
+            &expected.request, req,
+            "FakeHost: syscall did not match expectation {}",
+            next
+        );
+
+        if let Some(expected_buf) = &expected.buf {
+            let block = block.unwrap_or_else(|| {
+                panic!(
+                    "FakeHost: expectation {} staged a buffer, but no Block was given to compare against",
+                    next
+                )
+            });
+            assert_eq!(
+                &block.buf[..expected_buf.len],
+                &expected_buf.bytes[..expected_buf.len],
+                "FakeHost: buf mismatch for expectation {}",
+                next
+            );
+        }
+
+        let log_len = state.log_len;
+        state.log[log_len] = *req;
+        state.log_len = log_len + 1;
+        state.next = next + 1;
+        expected.reply
+    }
+}
+
+impl<const N: usize> Default for FakeHost<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Proxy for FakeHost<N> {
+    unsafe fn syscall(&self, req: &Request) -> Reply {
+        self.dispatch(req, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replays_scripted_syscalls_in_order() {
+        let host: FakeHost<2> = FakeHost::new();
+        let close = Request::new(libc::SYS_close, &[3usize.into()]);
+        let dup = Request::new(libc::SYS_dup, &[0usize.into()]);
+        host.expect(close, Err::<[memory::Register<usize>; 2], _>(libc::EBADF).into());
+        host.expect(dup, Ok::<_, libc::c_int>([3usize.into(), Default::default()]).into());
+
+        let rep = unsafe { host.syscall(&close) };
+        assert_eq!(Result::from(rep), Err(libc::EBADF));
+        let rep = unsafe { host.syscall(&dup) };
+        assert_eq!(
+            Result::from(rep),
+            Ok([3usize.into(), Default::default()])
+        );
+
+        host.finish();
+        assert_eq!(host.log()[0], close);
+        assert_eq!(host.log()[1], dup);
+    }
+
+    #[test]
+    fn checks_staged_buf_against_expectation() {
+        use crate::cursor::Cursor;
+        use core::mem::MaybeUninit;
+
+        // SAFETY: `Block` is a plain data buffer; an all-zero instance is a
+        // valid starting point for this test.
+        let mut block: Block = unsafe { MaybeUninit::zeroed().assume_init() };
+        let offset = Cursor::new(&mut block).write_slice(b"hello").unwrap();
+
+        let host: FakeHost<1> = FakeHost::new();
+        let req = Request::new(libc::SYS_write, &[1usize.into(), offset.into(), 5usize.into()]);
+        host.expect_buf(
+            req,
+            b"hello",
+            Ok::<_, libc::c_int>([5usize.into(), Default::default()]).into(),
+        );
+
+        let rep = host.syscall_with_buf(&req, &block);
+        assert_eq!(
+            Result::from(rep),
+            Ok([5usize.into(), Default::default()])
+        );
+        host.finish();
+    }
+
+    #[test]
+    #[should_panic(expected = "buf mismatch")]
+    fn panics_on_buf_mismatch() {
+        use crate::cursor::Cursor;
+        use core::mem::MaybeUninit;
+
+        // SAFETY: `Block` is a plain data buffer; an all-zero instance is a
+        // valid starting point for this test.
+        let mut block: Block = unsafe { MaybeUninit::zeroed().assume_init() };
+        let offset = Cursor::new(&mut block).write_slice(b"hello").unwrap();
+
+        let host: FakeHost<1> = FakeHost::new();
+        let req = Request::new(libc::SYS_write, &[1usize.into(), offset.into(), 5usize.into()]);
+        host.expect_buf(req, b"world", Reply::default());
+
+        host.syscall_with_buf(&req, &block);
+    }
+
+    #[test]
+    fn drives_real_syscall_via_through_fake_host() {
+        // Exercises the same `Request::syscall_via` entry point microkernel
+        // code proxies through, with `FakeHost` standing in for the host.
+        let host: FakeHost<1> = FakeHost::new();
+        let req = Request::new(libc::SYS_close, &[3usize.into()]);
+        host.expect(req, Ok::<_, libc::c_int>([0usize.into(), Default::default()]).into());
+
+        let rep = unsafe { req.syscall_via(&host) };
+        assert_eq!(
+            Result::from(rep),
+            Ok([0usize.into(), Default::default()])
+        );
+        host.finish();
+    }
+
+    #[test]
+    #[should_panic(expected = "unexpected syscall")]
+    fn panics_on_unexpected_syscall() {
+        let host: FakeHost<1> = FakeHost::new();
+        unsafe { host.syscall(&Request::new(libc::SYS_close, &[0usize.into()])) };
+    }
+
+    #[test]
+    #[should_panic(expected = "left unconsumed")]
+    fn panics_on_leftover_expectations() {
+        let host: FakeHost<1> = FakeHost::new();
+        host.expect(Request::new(libc::SYS_close, &[0usize.into()]), Reply::default());
+        host.finish();
+    }
+}